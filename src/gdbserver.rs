@@ -1,14 +1,206 @@
 use byteorder::{ByteOrder, LittleEndian};
 use ckb_vm::{
-    decoder::build_decoder, machine::asm::AsmMachine, CoreMachine, Memory, SupportMachine,
-    RISCV_GENERAL_REGISTER_NUMBER,
+    decoder::{build_decoder, Decoder},
+    instructions::{extract_opcode, insts, Instruction, Itype, Rtype, Stype},
+    machine::{
+        asm::{AsmCoreMachine, AsmMachine},
+        DefaultMachine,
+    },
+    CoreMachine, Memory, SupportMachine, RISCV_GENERAL_REGISTER_NUMBER,
 };
 use gdb_remote_protocol::{
     Breakpoint, Error, Handler, MemoryRegion, ProcessType, StopReason, ThreadId, VCont,
-    VContFeature,
+    VContFeature, WatchKind, Watchpoint,
 };
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::VecDeque;
+
+// A data watchpoint: the base address and length of the watched range together
+// with the kind of access that should trip it.
+type DataWatchpoint = (u64, u64, WatchKind);
+
+/// The VM configuration a gdbserver session was started with, surfaced to the
+/// connecting client over the `qSupported` exchange so it knows which hardfork
+/// semantics are being debugged.
+#[derive(Clone)]
+pub struct VmConfig {
+    pub isa: u8,
+    pub version: u32,
+    pub max_cycles: u64,
+    pub memory: String,
+    pub backend: String,
+}
+
+impl VmConfig {
+    // Renders the configuration as `qSupported` feature tokens.
+    fn feature_tokens(&self) -> Vec<String> {
+        vec![
+            format!("ckb-vm-isa={:#x}", self.isa),
+            format!("ckb-vm-version={}", self.version),
+            format!("ckb-vm-max-cycles={}", self.max_cycles),
+            format!("ckb-vm-memory={}", self.memory),
+            format!("ckb-vm-backend={}", self.backend),
+        ]
+    }
+}
+
+// Upper bound on the number of steps kept in the reverse-execution log. The log
+// is a ring buffer, so older steps fall off the back once this many are held.
+const UNDO_LOG_CAPACITY: usize = 4096;
+
+// A single undo record: the machine state before one forward step, plus the
+// bytes that step overwrote. Replaying it restores the machine to just before
+// that step.
+struct UndoRecord {
+    registers: [u64; RISCV_GENERAL_REGISTER_NUMBER],
+    pc: u64,
+    cycles: u64,
+    // Running flag captured before the step, so reversing across a step that
+    // halted the machine restores `running` and lets `vcont` report a trap
+    // rather than an exit.
+    running: bool,
+    // (address, original bytes) for every range the instruction overwrote,
+    // recorded in the order they were written so undo can reapply them in
+    // reverse.
+    memory: Vec<(u64, Vec<u8>)>,
+}
+
+// The slice of machine operations the gdb handler actually needs. Implementing
+// it for both the asm machine and the plain interpreting `DefaultMachine` lets
+// the same RSP frontend drive either execution engine.
+pub trait DebugTarget {
+    type Mem: Memory<REG = u64>;
+
+    fn registers(&self) -> &[u64];
+    fn pc(&self) -> u64;
+    fn set_register(&mut self, index: usize, value: u64);
+    fn update_pc(&mut self, pc: u64);
+    fn commit_pc(&mut self);
+    fn memory_mut(&mut self) -> &mut Self::Mem;
+    fn step(&mut self, decoder: &mut Decoder) -> Result<(), ckb_vm::Error>;
+    fn running(&self) -> bool;
+    fn set_running(&mut self, running: bool);
+    fn exit_code(&self) -> i8;
+    fn isa(&self) -> u8;
+    fn cycles(&self) -> u64;
+    fn set_cycles(&mut self, cycles: u64);
+}
+
+impl<'a, Inner> DebugTarget for DefaultMachine<'a, Inner>
+where
+    Inner: SupportMachine + CoreMachine<REG = u64>,
+{
+    type Mem = <Inner as CoreMachine>::MEM;
+
+    fn registers(&self) -> &[u64] {
+        CoreMachine::registers(self)
+    }
+
+    fn pc(&self) -> u64 {
+        *CoreMachine::pc(self)
+    }
+
+    fn set_register(&mut self, index: usize, value: u64) {
+        CoreMachine::set_register(self, index, value)
+    }
+
+    fn update_pc(&mut self, pc: u64) {
+        CoreMachine::update_pc(self, pc)
+    }
+
+    fn commit_pc(&mut self) {
+        CoreMachine::commit_pc(self)
+    }
+
+    fn memory_mut(&mut self) -> &mut Self::Mem {
+        CoreMachine::memory_mut(self)
+    }
+
+    fn step(&mut self, decoder: &mut Decoder) -> Result<(), ckb_vm::Error> {
+        DefaultMachine::step(self, decoder)
+    }
+
+    fn running(&self) -> bool {
+        SupportMachine::running(self)
+    }
+
+    fn set_running(&mut self, running: bool) {
+        SupportMachine::set_running(self, running)
+    }
+
+    fn exit_code(&self) -> i8 {
+        SupportMachine::exit_code(self)
+    }
+
+    fn isa(&self) -> u8 {
+        CoreMachine::isa(self)
+    }
+
+    fn cycles(&self) -> u64 {
+        SupportMachine::cycles(self)
+    }
+
+    fn set_cycles(&mut self, cycles: u64) {
+        SupportMachine::set_cycles(self, cycles)
+    }
+}
+
+impl<'a> DebugTarget for AsmMachine<'a> {
+    type Mem = <DefaultMachine<'a, Box<AsmCoreMachine>> as DebugTarget>::Mem;
+
+    fn registers(&self) -> &[u64] {
+        DebugTarget::registers(&self.machine)
+    }
+
+    fn pc(&self) -> u64 {
+        DebugTarget::pc(&self.machine)
+    }
+
+    fn set_register(&mut self, index: usize, value: u64) {
+        DebugTarget::set_register(&mut self.machine, index, value)
+    }
+
+    fn update_pc(&mut self, pc: u64) {
+        DebugTarget::update_pc(&mut self.machine, pc)
+    }
+
+    fn commit_pc(&mut self) {
+        DebugTarget::commit_pc(&mut self.machine)
+    }
+
+    fn memory_mut(&mut self) -> &mut Self::Mem {
+        DebugTarget::memory_mut(&mut self.machine)
+    }
+
+    fn step(&mut self, decoder: &mut Decoder) -> Result<(), ckb_vm::Error> {
+        DebugTarget::step(&mut self.machine, decoder)
+    }
+
+    fn running(&self) -> bool {
+        DebugTarget::running(&self.machine)
+    }
+
+    fn set_running(&mut self, running: bool) {
+        DebugTarget::set_running(&mut self.machine, running)
+    }
+
+    fn exit_code(&self) -> i8 {
+        DebugTarget::exit_code(&self.machine)
+    }
+
+    fn isa(&self) -> u8 {
+        DebugTarget::isa(&self.machine)
+    }
+
+    fn cycles(&self) -> u64 {
+        DebugTarget::cycles(&self.machine)
+    }
+
+    fn set_cycles(&mut self, cycles: u64) {
+        DebugTarget::set_cycles(&mut self.machine, cycles)
+    }
+}
 
 fn format_register_value(v: u64) -> Vec<u8> {
     let mut buf = [0u8; 8];
@@ -16,26 +208,243 @@ fn format_register_value(v: u64) -> Vec<u8> {
     buf.to_vec()
 }
 
-pub struct GdbHandler<'a> {
-    machine: RefCell<AsmMachine<'a>>,
+pub struct GdbHandler<T: DebugTarget> {
+    machine: RefCell<T>,
     breakpoints: RefCell<Vec<Breakpoint>>,
+    watchpoints: RefCell<Vec<DataWatchpoint>>,
+    // Cached bytes of every watched range, one entry per watchpoint at the
+    // same index as in `watchpoints`. Because
+    // ckb-vm's `Memory` exposes no native access hooks, write watchpoints are
+    // implemented by diffing these cached bytes against live memory after each
+    // step; the cache is seeded when a watchpoint is inserted and refreshed on
+    // every trip so that repeated writes each trigger once.
+    watch_cache: RefCell<Vec<Vec<u8>>>,
+    // Bounded ring buffer of undo records powering reverse execution. The
+    // newest step is at the back; reversing pops from there.
+    undo_log: RefCell<VecDeque<UndoRecord>>,
+    config: VmConfig,
 }
 
-impl<'a> GdbHandler<'a> {
+impl<T: DebugTarget> GdbHandler<T> {
     fn at_breakpoint(&self) -> bool {
-        let pc = *self.machine.borrow().machine.pc();
+        let pc = self.machine.borrow().pc();
         self.breakpoints.borrow().iter().any(|b| b.addr == pc)
     }
 
-    pub fn new(machine: AsmMachine<'a>) -> Self {
+    pub fn new(machine: T, config: VmConfig) -> Self {
         GdbHandler {
             machine: RefCell::new(machine),
             breakpoints: RefCell::new(vec![]),
+            watchpoints: RefCell::new(vec![]),
+            watch_cache: RefCell::new(vec![]),
+            undo_log: RefCell::new(VecDeque::with_capacity(UNDO_LOG_CAPACITY)),
+            config,
+        }
+    }
+
+    // Reads the current bytes of a watched range out of guest memory, falling
+    // back to zeroes for addresses that cannot be loaded (e.g. not yet mapped).
+    fn snapshot_range(&self, addr: u64, len: u64) -> Vec<u8> {
+        let mut machine = self.machine.borrow_mut();
+        let memory = machine.memory_mut();
+        (0..len)
+            .map(|i| memory.load8(&(addr + i)).map(|v| v as u8).unwrap_or(0))
+            .collect()
+    }
+
+    // Decodes the effective address and width of a load instruction, if it is
+    // one. Used to approximate read watchpoints, which ckb-vm cannot observe
+    // directly.
+    fn load_effective_address(&self, inst: Instruction) -> Option<(u64, u64)> {
+        let size = match extract_opcode(inst) {
+            insts::OP_LB | insts::OP_LBU => 1,
+            insts::OP_LH | insts::OP_LHU => 2,
+            insts::OP_LW | insts::OP_LWU => 4,
+            insts::OP_LD => 8,
+            _ => return None,
+        };
+        let i = Itype(inst);
+        let base = self.machine.borrow().registers()[i.rs1()];
+        let addr = base.wrapping_add(i.immediate_s() as i64 as u64);
+        Some((addr, size))
+    }
+
+    // Returns true when the range `[addr, addr+len)` overlaps any read-capable
+    // watchpoint.
+    fn overlaps_read_watchpoint(&self, addr: u64, len: u64) -> bool {
+        self.watchpoints.borrow().iter().any(|(w_addr, w_len, kind)| {
+            matches!(kind, WatchKind::Read | WatchKind::ReadWrite)
+                && addr < w_addr + w_len
+                && *w_addr < addr + len
+        })
+    }
+
+    // Compares every watched range against its cache; if any write-capable
+    // watchpoint changed, refreshes the cache and reports that a stop is due.
+    fn hit_write_watchpoint(&self) -> bool {
+        let watchpoints = self.watchpoints.borrow();
+        let mut hit = false;
+        for (index, (addr, len, kind)) in watchpoints.iter().enumerate() {
+            if !matches!(kind, WatchKind::Write | WatchKind::ReadWrite) {
+                continue;
+            }
+            let current = self.snapshot_range(*addr, *len);
+            if current != self.watch_cache.borrow()[index] {
+                self.watch_cache.borrow_mut()[index] = current;
+                hit = true;
+            }
+        }
+        hit
+    }
+
+    // Decodes the effective address and width of a memory-writing instruction,
+    // if it is one. Used to capture the bytes a step is about to overwrite for
+    // the undo log, bounding per-step memory logging to the single written
+    // range.
+    //
+    // Two instruction shapes write memory. Plain stores (`OP_S*`) address memory
+    // through an S-type base+immediate; ckb-vm's decoder expands compressed
+    // stores (C.SW/C.SD/C.SWSP/C.SDSP) into these canonical opcodes before they
+    // reach us, so matching the uncompressed opcodes here also covers their RVC
+    // forms. The atomic `A` extension (reachable once `--isa a` is enabled) also
+    // writes memory: the AMO read-modify-writes and the store-conditionals
+    // (`OP_SC_*`) address memory through `rs1` directly with no immediate.
+    // `OP_LR_*` is load-reserved and writes nothing, so it is excluded.
+    fn store_effective_address(&self, inst: Instruction) -> Option<(u64, u64)> {
+        match extract_opcode(inst) {
+            insts::OP_SB => Some((self.stype_address(inst), 1)),
+            insts::OP_SH => Some((self.stype_address(inst), 2)),
+            insts::OP_SW => Some((self.stype_address(inst), 4)),
+            insts::OP_SD => Some((self.stype_address(inst), 8)),
+            insts::OP_AMOSWAP_W
+            | insts::OP_AMOADD_W
+            | insts::OP_AMOXOR_W
+            | insts::OP_AMOAND_W
+            | insts::OP_AMOOR_W
+            | insts::OP_AMOMIN_W
+            | insts::OP_AMOMAX_W
+            | insts::OP_AMOMINU_W
+            | insts::OP_AMOMAXU_W
+            | insts::OP_SC_W => Some((self.amo_address(inst), 4)),
+            insts::OP_AMOSWAP_D
+            | insts::OP_AMOADD_D
+            | insts::OP_AMOXOR_D
+            | insts::OP_AMOAND_D
+            | insts::OP_AMOOR_D
+            | insts::OP_AMOMIN_D
+            | insts::OP_AMOMAX_D
+            | insts::OP_AMOMINU_D
+            | insts::OP_AMOMAXU_D
+            | insts::OP_SC_D => Some((self.amo_address(inst), 8)),
+            _ => None,
+        }
+    }
+
+    // Effective address of an S-type store: base register plus signed immediate.
+    fn stype_address(&self, inst: Instruction) -> u64 {
+        let s = Stype(inst);
+        let base = self.machine.borrow().registers()[s.rs1()];
+        base.wrapping_add(s.immediate_s() as i64 as u64)
+    }
+
+    // Effective address of an atomic memory operation: the `rs1` register value
+    // with no immediate offset.
+    fn amo_address(&self, inst: Instruction) -> u64 {
+        let r = Rtype(inst);
+        self.machine.borrow().registers()[r.rs1()]
+    }
+
+    // Pushes a record onto the undo log, evicting the oldest step once the ring
+    // buffer is full so reverse-execution memory stays bounded.
+    fn push_undo(&self, record: UndoRecord) {
+        let mut log = self.undo_log.borrow_mut();
+        if log.len() == UNDO_LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(record);
+    }
+
+    // Steps the machine once forward, recording an undo entry and reporting
+    // whether any data watchpoint tripped. Read watchpoints are checked against
+    // the load address of the instruction that is about to execute; write
+    // watchpoints are diffed afterwards.
+    fn step_watched(&self, decoder: &mut Decoder) -> Result<bool, Error> {
+        let pc = self.machine.borrow().pc();
+        let inst = decoder
+            .decode(self.machine.borrow_mut().memory_mut(), pc)
+            .map_err(|e| {
+                error!("Error decoding instruction at {:x}: {:?}", pc, e);
+                Error::Error(5)
+            })?;
+
+        let has_read_watch = self
+            .watchpoints
+            .borrow()
+            .iter()
+            .any(|(_, _, kind)| matches!(kind, WatchKind::Read | WatchKind::ReadWrite));
+        let read_hit = if has_read_watch {
+            match self.load_effective_address(inst) {
+                Some((addr, len)) => self.overlaps_read_watchpoint(addr, len),
+                None => false,
+            }
+        } else {
+            false
+        };
+
+        // Snapshot the pre-step state for the undo log, including the bytes this
+        // instruction is about to overwrite.
+        let mut registers = [0u64; RISCV_GENERAL_REGISTER_NUMBER];
+        registers.copy_from_slice(&self.machine.borrow().registers()[..RISCV_GENERAL_REGISTER_NUMBER]);
+        let cycles = self.machine.borrow().cycles();
+        let running = self.machine.borrow().running();
+        let memory = match self.store_effective_address(inst) {
+            Some((addr, size)) => vec![(addr, self.snapshot_range(addr, size))],
+            None => vec![],
+        };
+
+        self.machine.borrow_mut().step(decoder).expect("VM error");
+        self.push_undo(UndoRecord {
+            registers,
+            pc,
+            cycles,
+            running,
+            memory,
+        });
+
+        // Evaluate the write-watch diff unconditionally: short-circuiting it on a
+        // read hit would leave a changed write-watched range stale in the cache
+        // and trip a spurious stop on the next step.
+        let write_hit = self.hit_write_watchpoint();
+        Ok(read_hit || write_hit)
+    }
+
+    // Reverses one forward step by restoring the newest undo record. Returns
+    // false when the log is exhausted and no further reversal is possible.
+    fn reverse_step(&self) -> bool {
+        let record = match self.undo_log.borrow_mut().pop_back() {
+            Some(record) => record,
+            None => return false,
+        };
+        let mut machine = self.machine.borrow_mut();
+        for (index, value) in record.registers.iter().enumerate() {
+            machine.set_register(index, *value);
         }
+        machine.update_pc(record.pc);
+        machine.commit_pc();
+        machine.set_cycles(record.cycles);
+        machine.set_running(record.running);
+        // Reapply the overwritten bytes in reverse write order.
+        for (addr, bytes) in record.memory.iter().rev() {
+            machine
+                .memory_mut()
+                .store_bytes(*addr, bytes)
+                .expect("restore memory");
+        }
+        true
     }
 }
 
-impl<'a> Handler for GdbHandler<'a> {
+impl<T: DebugTarget> Handler for GdbHandler<T> {
     fn attached(&self, _pid: Option<u64>) -> Result<ProcessType, Error> {
         Ok(ProcessType::Created)
     }
@@ -49,7 +458,6 @@ impl<'a> Handler for GdbHandler<'a> {
         let registers: Vec<Vec<u8>> = self
             .machine
             .borrow()
-            .machine
             .registers()
             .iter()
             .map(|v| format_register_value(*v))
@@ -61,10 +469,10 @@ impl<'a> Handler for GdbHandler<'a> {
         let register = register as usize;
         if register < RISCV_GENERAL_REGISTER_NUMBER {
             Ok(format_register_value(
-                self.machine.borrow().machine.registers()[register],
+                self.machine.borrow().registers()[register],
             ))
         } else if register == RISCV_GENERAL_REGISTER_NUMBER {
-            Ok(format_register_value(*self.machine.borrow().machine.pc()))
+            Ok(format_register_value(self.machine.borrow().pc()))
         } else {
             Err(Error::Error(1))
         }
@@ -80,14 +488,11 @@ impl<'a> Handler for GdbHandler<'a> {
         let value = LittleEndian::read_u64(&buffer[..]);
         let register = register as usize;
         if register < RISCV_GENERAL_REGISTER_NUMBER {
-            self.machine
-                .borrow_mut()
-                .machine
-                .set_register(register, value);
+            self.machine.borrow_mut().set_register(register, value);
             Ok(())
         } else if register == RISCV_GENERAL_REGISTER_NUMBER {
-            self.machine.borrow_mut().machine.update_pc(value);
-            self.machine.borrow_mut().machine.commit_pc();
+            self.machine.borrow_mut().update_pc(value);
+            self.machine.borrow_mut().commit_pc();
             Ok(())
         } else {
             Err(Error::Error(2))
@@ -100,7 +505,6 @@ impl<'a> Handler for GdbHandler<'a> {
             let value = self
                 .machine
                 .borrow_mut()
-                .machine
                 .memory_mut()
                 .load8(&address)
                 .map_err(|e| {
@@ -115,7 +519,6 @@ impl<'a> Handler for GdbHandler<'a> {
     fn write_memory(&self, address: u64, bytes: &[u8]) -> Result<(), Error> {
         self.machine
             .borrow_mut()
-            .machine
             .memory_mut()
             .store_bytes(address, bytes)
             .map_err(|e| {
@@ -125,6 +528,18 @@ impl<'a> Handler for GdbHandler<'a> {
         Ok(())
     }
 
+    fn query_supported_features(&self) -> Vec<String> {
+        // Advertise the session's VM configuration alongside the usual
+        // capabilities so the client learns the active ISA/version/memory model
+        // from the qSupported reply. We deliberately do not advertise
+        // `qXfer:features:read+`: there is no target description reader here, so
+        // claiming it would invite the client to request a target.xml we never
+        // serve.
+        let mut features = vec![String::from("PacketSize=4096")];
+        features.extend(self.config.feature_tokens());
+        features
+    }
+
     fn query_supported_vcont(&self) -> Result<Cow<'static, [VContFeature]>, Error> {
         // Even though we won't support all of vCont features, gdb feature
         // detection only work when we include all of them. The other solution
@@ -138,68 +553,65 @@ impl<'a> Handler for GdbHandler<'a> {
                 VContFeature::StepWithSignal,
                 VContFeature::Stop,
                 VContFeature::RangeStep,
+                VContFeature::ReverseStep,
+                VContFeature::ReverseContinue,
             ][..],
         ))
     }
 
     fn vcont(&self, request: Vec<(VCont, Option<ThreadId>)>) -> Result<StopReason, Error> {
-        let mut decoder = build_decoder::<u64>(self.machine.borrow().machine.isa());
+        let mut decoder = build_decoder::<u64>(self.machine.borrow().isa());
         let (vcont, _thread_id) = &request[0];
         match vcont {
             VCont::Continue => {
-                self.machine
-                    .borrow_mut()
-                    .machine
-                    .step(&mut decoder)
-                    .expect("VM error");
-                while (!self.at_breakpoint()) && self.machine.borrow().machine.running() {
-                    self.machine
-                        .borrow_mut()
-                        .machine
-                        .step(&mut decoder)
-                        .expect("VM error");
+                if self.step_watched(&mut decoder)? {
+                    return Ok(StopReason::Signal(5));
+                }
+                while (!self.at_breakpoint()) && self.machine.borrow().running() {
+                    if self.step_watched(&mut decoder)? {
+                        return Ok(StopReason::Signal(5));
+                    }
                 }
             }
             VCont::Step => {
-                if self.machine.borrow().machine.running() {
-                    self.machine
-                        .borrow_mut()
-                        .machine
-                        .step(&mut decoder)
-                        .expect("VM error");
+                if self.machine.borrow().running() && self.step_watched(&mut decoder)? {
+                    return Ok(StopReason::Signal(5));
                 }
             }
             VCont::RangeStep(range) => {
-                self.machine
-                    .borrow_mut()
-                    .machine
-                    .step(&mut decoder)
-                    .expect("VM error");
-                while self.machine.borrow().machine.pc() >= &range.start
-                    && self.machine.borrow().machine.pc() < &range.end
+                if self.step_watched(&mut decoder)? {
+                    return Ok(StopReason::Signal(5));
+                }
+                while self.machine.borrow().pc() >= range.start
+                    && self.machine.borrow().pc() < range.end
                     && (!self.at_breakpoint())
-                    && self.machine.borrow().machine.running()
+                    && self.machine.borrow().running()
                 {
-                    self.machine
-                        .borrow_mut()
-                        .machine
-                        .step(&mut decoder)
-                        .expect("VM error");
+                    if self.step_watched(&mut decoder)? {
+                        return Ok(StopReason::Signal(5));
+                    }
                 }
             }
+            VCont::ReverseStep => {
+                // Undo one forward step; if the log is empty this is a no-op and
+                // we simply report the unchanged state back.
+                self.reverse_step();
+            }
+            VCont::ReverseContinue => {
+                // Walk the undo log backwards until a breakpoint is hit or the
+                // log bottoms out, then stop cleanly either way.
+                while self.reverse_step() && !self.at_breakpoint() {}
+            }
             v => {
                 debug!("Unspported vcont type: {:?}", v);
                 return Err(Error::Error(5));
             }
         }
-        if self.machine.borrow().machine.running() {
+        if self.machine.borrow().running() {
             // SIGTRAP
             Ok(StopReason::Signal(5))
         } else {
-            Ok(StopReason::Exited(
-                0,
-                self.machine.borrow().machine.exit_code() as u8,
-            ))
+            Ok(StopReason::Exited(0, self.machine.borrow().exit_code() as u8))
         }
     }
 
@@ -212,4 +624,99 @@ impl<'a> Handler for GdbHandler<'a> {
         self.breakpoints.borrow_mut().retain(|b| b != &breakpoint);
         Ok(())
     }
+
+    fn insert_hardware_watchpoint(&self, watchpoint: Watchpoint) -> Result<(), Error> {
+        let entry = (watchpoint.addr, watchpoint.n_bytes, watchpoint.kind);
+        // Seed the cache with the current bytes so the first later write is what
+        // trips the watchpoint, not the state it already held at insert time.
+        let cache = self.snapshot_range(watchpoint.addr, watchpoint.n_bytes);
+        self.watchpoints.borrow_mut().push(entry);
+        self.watch_cache.borrow_mut().push(cache);
+        Ok(())
+    }
+
+    fn remove_hardware_watchpoint(&self, watchpoint: Watchpoint) -> Result<(), Error> {
+        let entry = (watchpoint.addr, watchpoint.n_bytes, watchpoint.kind);
+        let mut watchpoints = self.watchpoints.borrow_mut();
+        if let Some(index) = watchpoints.iter().position(|w| w == &entry) {
+            watchpoints.remove(index);
+            self.watch_cache.borrow_mut().remove(index);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DebugTarget, GdbHandler, Memory, UndoRecord, VmConfig, RISCV_GENERAL_REGISTER_NUMBER};
+    use ckb_vm::{
+        machine::VERSION0, DefaultCoreMachine, DefaultMachineBuilder, SparseMemory, ISA_IMC,
+    };
+
+    fn config() -> VmConfig {
+        VmConfig {
+            isa: ISA_IMC,
+            version: VERSION0,
+            max_cycles: u64::max_value(),
+            memory: String::from("sparse"),
+            backend: String::from("interpreter"),
+        }
+    }
+
+    #[test]
+    fn reverse_step_restores_state_including_running_flag() {
+        let core =
+            DefaultCoreMachine::<u64, SparseMemory<u64>>::new(ISA_IMC, VERSION0, u64::max_value());
+        let machine = DefaultMachineBuilder::new(core).build();
+        let handler = GdbHandler::new(machine, config());
+
+        // Record the pre-step state, including the byte the step would overwrite.
+        handler
+            .machine
+            .borrow_mut()
+            .memory_mut()
+            .store_bytes(0x2000, &[0xAA])
+            .unwrap();
+        let mut registers = [0u64; RISCV_GENERAL_REGISTER_NUMBER];
+        registers[1] = 100;
+        handler.push_undo(UndoRecord {
+            registers,
+            pc: 0x1000,
+            cycles: 5,
+            running: true,
+            memory: vec![(0x2000, vec![0xAA])],
+        });
+
+        // Simulate a forward step that advanced the machine and halted it.
+        {
+            let mut m = handler.machine.borrow_mut();
+            m.set_register(1, 999);
+            m.update_pc(0x9999);
+            m.commit_pc();
+            m.set_cycles(50);
+            m.set_running(false);
+            m.memory_mut().store_bytes(0x2000, &[0xBB]).unwrap();
+        }
+
+        assert!(handler.reverse_step());
+        {
+            let m = handler.machine.borrow();
+            assert_eq!(m.registers()[1], 100);
+            assert_eq!(m.pc(), 0x1000);
+            assert_eq!(m.cycles(), 5);
+            // The running flag is restored, so a later exit does not leak across
+            // the reversed step.
+            assert!(m.running());
+        }
+        let byte = handler
+            .machine
+            .borrow_mut()
+            .memory_mut()
+            .load8(&0x2000)
+            .unwrap();
+        assert_eq!(byte, 0xAA);
+
+        // The log is now empty, so further reversal is a no-op.
+        assert!(!handler.reverse_step());
+    }
 }
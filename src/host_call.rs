@@ -0,0 +1,78 @@
+use ckb_vm::{
+    registers::{A0, A1, A2, A3, A7},
+    Error, Memory, Register, SupportMachine, Syscalls,
+};
+use std::collections::HashMap;
+
+/// A single host function: it receives the serialized argument buffer the guest
+/// marshaled and returns the serialized reply.
+type HostCall = Box<dyn FnMut(&[u8]) -> Vec<u8>>;
+
+/// A registry of host functions keyed by syscall number, modeled on an RPC
+/// bridge.
+///
+/// The calling convention mirrors the `rpc_send`/`rpc_recv` split used by
+/// embedded RISC-V runtimes: the guest marshals its arguments into a flat
+/// slice-of-slices, points `A0`/`A1` at that buffer, points `A2`/`A3` at a
+/// buffer to receive the reply, and traps with the syscall number in `A7`. A
+/// single host call reads the arguments, runs the registered closure, writes the
+/// serialized reply back into the return buffer, and sets `A0` to the number of
+/// bytes written.
+///
+/// Debug harnesses register closures to stub out chain syscalls or inject test
+/// fixtures without patching the VM itself.
+#[derive(Default)]
+pub struct HostCallRegistry {
+    calls: HashMap<u64, HostCall>,
+}
+
+impl HostCallRegistry {
+    pub fn new() -> Self {
+        HostCallRegistry {
+            calls: HashMap::new(),
+        }
+    }
+
+    /// Registers a host function under `number`, replacing any previous entry
+    /// for that syscall number.
+    pub fn register<F: FnMut(&[u8]) -> Vec<u8> + 'static>(
+        &mut self,
+        number: u64,
+        call: F,
+    ) -> &mut Self {
+        self.calls.insert(number, Box::new(call));
+        self
+    }
+}
+
+impl<Mac: SupportMachine> Syscalls<Mac> for HostCallRegistry {
+    fn initialize(&mut self, _machine: &mut Mac) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn ecall(&mut self, machine: &mut Mac) -> Result<bool, Error> {
+        let number = machine.registers()[A7].to_u64();
+        let call = match self.calls.get_mut(&number) {
+            Some(call) => call,
+            None => return Ok(false),
+        };
+
+        // rpc_send: gather the serialized argument buffer from guest memory.
+        let arg_addr = machine.registers()[A0].to_u64();
+        let arg_len = machine.registers()[A1].to_u64();
+        let args = machine.memory_mut().load_bytes(arg_addr, arg_len)?;
+
+        let result = call(&args);
+
+        // rpc_recv: scatter the serialized reply back into the guest-provided
+        // return buffer, bounded by its capacity, and report the byte count.
+        let ret_addr = machine.registers()[A2].to_u64();
+        let ret_cap = machine.registers()[A3].to_u64();
+        let written = (result.len() as u64).min(ret_cap);
+        machine
+            .memory_mut()
+            .store_bytes(ret_addr, &result[..written as usize])?;
+        machine.set_register(A0, Mac::REG::from_u64(written));
+        Ok(true)
+    }
+}
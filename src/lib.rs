@@ -3,8 +3,10 @@ extern crate log;
 
 mod elf_dumper;
 mod gdbserver;
+mod host_call;
 mod stdio;
 
 pub use elf_dumper::ElfDumper;
-pub use gdbserver::GdbHandler;
+pub use gdbserver::{DebugTarget, GdbHandler, VmConfig};
+pub use host_call::HostCallRegistry;
 pub use stdio::Stdio;
@@ -1,49 +1,182 @@
 #[macro_use]
 extern crate log;
 
+use argh::FromArgs;
 use bytes::Bytes;
 use ckb_vm::{
-    DefaultCoreMachine, DefaultMachineBuilder, SparseMemory, SupportMachine, WXorXMemory, ISA_B,
-    ISA_IMC, ISA_MOP,
+    machine::{asm::AsmCoreMachine, asm::AsmMachine, VERSION0, VERSION1},
+    DefaultCoreMachine, DefaultMachineBuilder, FlatMemory, SparseMemory, SupportMachine,
+    WXorXMemory, ISA_A, ISA_B, ISA_IMC, ISA_MOP,
 };
-use ckb_vm_debug_utils::{GdbHandler, Stdio};
+use ckb_vm_debug_utils::{DebugTarget, GdbHandler, Stdio, VmConfig};
 use gdb_remote_protocol::process_packets_from;
-use std::env;
 use std::fs::File;
 use std::io::Read;
-use std::net::TcpListener;
+use std::net::{TcpListener, TcpStream};
+use std::process::exit;
+
+/// A gdbserver frontend for debugging CKB-VM programs.
+#[derive(FromArgs)]
+struct Args {
+    /// comma separated list of ISA extensions to enable (imc,a,b,mop)
+    #[argh(option, default = "String::from(\"imc,b,mop\")")]
+    isa: String,
+    /// CKB-VM version selecting the active hardfork semantics (0 or 1)
+    #[argh(option, default = "1")]
+    vm_version: u32,
+    /// register width in bits; only 64 is supported (32-bit cores are not yet
+    /// implemented)
+    #[argh(option, default = "64")]
+    bits: u32,
+    /// maximum number of cycles the program may consume
+    #[argh(option, default = "u64::max_value()")]
+    max_cycles: u64,
+    /// execution engine to debug (asm or interpreter)
+    #[argh(option, default = "String::from(\"asm\")")]
+    backend: String,
+    /// guest memory model for the interpreter backend (sparse or flat)
+    #[argh(option, default = "String::from(\"sparse\")")]
+    memory: String,
+    /// address to listen on, e.g. 127.0.0.1:9999
+    #[argh(positional)]
+    listen: String,
+    /// path to the program to debug
+    #[argh(positional)]
+    program: String,
+    /// arguments passed to the debugged program
+    #[argh(positional)]
+    program_args: Vec<String>,
+}
+
+fn parse_isa(spec: &str) -> u8 {
+    let mut isa = 0;
+    for part in spec.split(',') {
+        match part.trim() {
+            "" => {}
+            "imc" => isa |= ISA_IMC,
+            "a" => isa |= ISA_A,
+            "b" => isa |= ISA_B,
+            "mop" => isa |= ISA_MOP,
+            other => {
+                eprintln!("Unknown ISA extension: {}", other);
+                exit(1);
+            }
+        }
+    }
+    isa
+}
+
+fn parse_version(version: u32) -> u32 {
+    match version {
+        0 => VERSION0,
+        1 => VERSION1,
+        other => {
+            eprintln!("Unsupported VM version: {}", other);
+            exit(1);
+        }
+    }
+}
+
+fn serve<T: DebugTarget>(machine: T, config: VmConfig, stream: TcpStream) {
+    let h = GdbHandler::new(machine, config);
+    process_packets_from(stream.try_clone().unwrap(), stream, h);
+}
 
 fn main() {
     drop(env_logger::init());
-    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Args = argh::from_env();
+
+    let isa = parse_isa(&args.isa);
+    let version = parse_version(args.vm_version);
+
+    // Only the 64-bit core is wired up here. Reject 32-bit explicitly rather
+    // than silently debugging a 64-bit core under a `--bits 32` request.
+    if args.bits != 64 {
+        eprintln!(
+            "Unsupported register width: {} (only 64-bit cores are implemented)",
+            args.bits
+        );
+        exit(1);
+    }
+
+    let config = VmConfig {
+        isa,
+        version,
+        max_cycles: args.max_cycles,
+        memory: args.memory.clone(),
+        backend: args.backend.clone(),
+    };
 
-    let listener = TcpListener::bind(&args[0]).expect("listen");
-    debug!("Listening on {}", args[0]);
+    let listener = TcpListener::bind(&args.listen).expect("listen");
+    debug!(
+        "Listening on {}, isa={:#x}, version={}, max_cycles={}, backend={}, memory={}",
+        args.listen, isa, args.vm_version, args.max_cycles, args.backend, args.memory
+    );
 
-    let mut file = File::open(&args[1]).expect("open program");
+    let mut file = File::open(&args.program).expect("open program");
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer).unwrap();
     let program: Bytes = buffer.into();
-    let program_args: Vec<Bytes> = args.into_iter().skip(1).map(|a| a.into()).collect();
+    let mut program_args: Vec<Bytes> = vec![args.program.clone().into()];
+    program_args.extend(args.program_args.iter().map(|a| a.clone().into()));
 
     for res in listener.incoming() {
         debug!("Got connection");
         if let Ok(stream) = res {
-            // TODO: vm version and isa should be configurable in the future.
-            let machine_core = DefaultCoreMachine::<u64, WXorXMemory<SparseMemory<u64>>>::new(
-                ISA_IMC | ISA_B | ISA_MOP,
-                1,
-                u64::max_value(),
-            );
-            let mut machine = DefaultMachineBuilder::new(machine_core)
-                .syscall(Box::new(Stdio::new(true)))
-                .build();
-            machine
-                .load_program(&program, &program_args)
-                .expect("load program");
-            machine.set_running(true);
-            let h = GdbHandler::new(machine);
-            process_packets_from(stream.try_clone().unwrap(), stream, h);
+            match args.backend.as_str() {
+                "asm" => {
+                    let asm_core = AsmCoreMachine::new(isa, version, args.max_cycles);
+                    let core = DefaultMachineBuilder::new(asm_core)
+                        .syscall(Box::new(Stdio::new(true)))
+                        .build();
+                    let mut machine = AsmMachine::new(core, None);
+                    machine
+                        .load_program(&program, &program_args)
+                        .expect("load program");
+                    SupportMachine::set_running(&mut machine.machine, true);
+                    serve(machine, config.clone(), stream);
+                }
+                "interpreter" | "interp" => match args.memory.as_str() {
+                    "sparse" => {
+                        let core = DefaultCoreMachine::<u64, WXorXMemory<SparseMemory<u64>>>::new(
+                            isa,
+                            version,
+                            args.max_cycles,
+                        );
+                        let mut machine = DefaultMachineBuilder::new(core)
+                            .syscall(Box::new(Stdio::new(true)))
+                            .build();
+                        machine
+                            .load_program(&program, &program_args)
+                            .expect("load program");
+                        SupportMachine::set_running(&mut machine, true);
+                        serve(machine, config.clone(), stream);
+                    }
+                    "flat" => {
+                        let core = DefaultCoreMachine::<u64, WXorXMemory<FlatMemory<u64>>>::new(
+                            isa,
+                            version,
+                            args.max_cycles,
+                        );
+                        let mut machine = DefaultMachineBuilder::new(core)
+                            .syscall(Box::new(Stdio::new(true)))
+                            .build();
+                        machine
+                            .load_program(&program, &program_args)
+                            .expect("load program");
+                        SupportMachine::set_running(&mut machine, true);
+                        serve(machine, config.clone(), stream);
+                    }
+                    other => {
+                        eprintln!("Unknown memory model: {}", other);
+                        exit(1);
+                    }
+                },
+                other => {
+                    eprintln!("Unknown backend: {}", other);
+                    exit(1);
+                }
+            }
         }
         debug!("Connection closed");
     }
@@ -1,11 +1,28 @@
 use ckb_vm::{
-    registers::{A0, A1, A7},
+    registers::{A0, A1, A2, A3, A7},
     Error, Memory, Register, SupportMachine, Syscalls,
 };
-use nix::sys::stat::fstat;
+use nix::errno::Errno;
+use nix::fcntl::{openat, OFlag};
+use nix::sys::stat::{fstat, Mode};
+use nix::sys::uio::{readv, writev, IoVec};
+use nix::unistd::{close, lseek, read, write, Whence};
+use std::collections::HashMap;
 use std::mem::size_of;
+use std::os::unix::io::RawFd;
 use std::slice::from_raw_parts;
 
+// Upper bound on the number of bytes a single fd syscall may buffer, and on the
+// number of iovec entries it may gather. Guest-controlled lengths are clamped
+// or rejected against these so a hostile length cannot abort the debugger on an
+// allocation failure.
+const MAX_IO_BYTES: usize = 16 * 1024 * 1024;
+const IOV_MAX: u64 = 1024;
+
+// Sentinel dirfd meaning "resolve relative paths against the current working
+// directory"; it is not a real descriptor and bypasses fd translation.
+const AT_FDCWD: RawFd = nix::libc::AT_FDCWD;
+
 #[derive(Clone, Debug, Default)]
 #[repr(C)]
 struct AbiStat {
@@ -31,15 +48,317 @@ struct AbiStat {
     __unused5: i32,
 }
 
-pub struct Stdio {}
+pub struct Stdio {
+    // Translation table mapping guest file descriptors onto the host fds they
+    // are allowed to touch. Only fds present here can be operated on, so a
+    // guest cannot reach arbitrary host descriptors.
+    fds: HashMap<u64, RawFd>,
+    next_fd: u64,
+}
 
 impl Stdio {
+    pub fn new(inherit_std: bool) -> Self {
+        let mut fds = HashMap::new();
+        if inherit_std {
+            fds.insert(0, 0);
+            fds.insert(1, 1);
+            fds.insert(2, 2);
+        }
+        Stdio { fds, next_fd: 3 }
+    }
+
+    fn host_fd(&self, guest_fd: u64) -> Result<RawFd, Errno> {
+        self.fds.get(&guest_fd).copied().ok_or(Errno::EBADF)
+    }
+
+    fn set_errno<Mac: SupportMachine>(machine: &mut Mac, errno: Errno) {
+        machine.set_register(A0, Mac::REG::from_i64(-(errno as i64)));
+    }
+
+    fn read<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let guest_fd = machine.registers()[A0].to_u64();
+        let addr = machine.registers()[A1].to_u64();
+        // A short read is allowed, so clamping an oversized request is safe.
+        let len = (machine.registers()[A2].to_u64() as usize).min(MAX_IO_BYTES);
+        let fd = match self.host_fd(guest_fd) {
+            Ok(fd) => fd,
+            Err(e) => {
+                Self::set_errno(machine, e);
+                return Ok(());
+            }
+        };
+        let mut buffer = vec![0u8; len];
+        match read(fd, &mut buffer) {
+            Ok(read_len) => {
+                machine.memory_mut().store_bytes(addr, &buffer[..read_len])?;
+                machine.set_register(A0, Mac::REG::from_u64(read_len as u64));
+            }
+            Err(e) => Self::set_errno(machine, e),
+        }
+        Ok(())
+    }
+
+    fn write<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let guest_fd = machine.registers()[A0].to_u64();
+        let addr = machine.registers()[A1].to_u64();
+        // A short write is allowed, so clamping an oversized request is safe and
+        // keeps the bound symmetric with `read`.
+        let len = (machine.registers()[A2].to_u64() as usize).min(MAX_IO_BYTES) as u64;
+        let fd = match self.host_fd(guest_fd) {
+            Ok(fd) => fd,
+            Err(e) => {
+                Self::set_errno(machine, e);
+                return Ok(());
+            }
+        };
+        let buffer = machine.memory_mut().load_bytes(addr, len)?;
+        match write(fd, &buffer) {
+            Ok(write_len) => machine.set_register(A0, Mac::REG::from_u64(write_len as u64)),
+            Err(e) => Self::set_errno(machine, e),
+        }
+        Ok(())
+    }
+
+    // Reads the guest `struct iovec` array (each entry is an 8-byte base
+    // pointer followed by an 8-byte length) into a list of (addr, len) pairs.
+    fn load_iovec<Mac: SupportMachine>(
+        machine: &mut Mac,
+        addr: u64,
+        count: u64,
+    ) -> Result<Vec<(u64, u64)>, Error> {
+        let mut iovec = Vec::with_capacity((count as usize).min(IOV_MAX as usize));
+        for i in 0..count {
+            let entry = addr + i * 16;
+            let base = machine.memory_mut().load64(&Mac::REG::from_u64(entry))?;
+            let len = machine
+                .memory_mut()
+                .load64(&Mac::REG::from_u64(entry + 8))?;
+            iovec.push((base.to_u64(), len.to_u64()));
+        }
+        Ok(iovec)
+    }
+
+    // Total number of bytes addressed by an iovec array, saturating so a crafted
+    // set of lengths cannot overflow the bound check.
+    fn total_len(iovec: &[(u64, u64)]) -> u64 {
+        iovec.iter().fold(0u64, |acc, (_, len)| acc.saturating_add(*len))
+    }
+
+    fn readv<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let guest_fd = machine.registers()[A0].to_u64();
+        let iov_addr = machine.registers()[A1].to_u64();
+        let iov_count = machine.registers()[A2].to_u64();
+        let fd = match self.host_fd(guest_fd) {
+            Ok(fd) => fd,
+            Err(e) => {
+                Self::set_errno(machine, e);
+                return Ok(());
+            }
+        };
+        if iov_count > IOV_MAX {
+            Self::set_errno(machine, Errno::EINVAL);
+            return Ok(());
+        }
+        let iovec = Self::load_iovec(machine, iov_addr, iov_count)?;
+        if Self::total_len(&iovec) > MAX_IO_BYTES as u64 {
+            Self::set_errno(machine, Errno::EINVAL);
+            return Ok(());
+        }
+        // Allocate one host buffer per guest range and fill them all with a
+        // single readv, rather than issuing one read per entry.
+        let mut buffers: Vec<Vec<u8>> =
+            iovec.iter().map(|(_, len)| vec![0u8; *len as usize]).collect();
+        let read_len = {
+            let mut slices: Vec<IoVec<&mut [u8]>> = buffers
+                .iter_mut()
+                .map(|b| IoVec::from_mut_slice(&mut b[..]))
+                .collect();
+            match readv(fd, &mut slices) {
+                Ok(len) => len,
+                Err(e) => {
+                    Self::set_errno(machine, e);
+                    return Ok(());
+                }
+            }
+        };
+        // Scatter the bytes that were actually read back into guest memory.
+        Self::scatter(machine, &iovec, &buffers, read_len)?;
+        machine.set_register(A0, Mac::REG::from_u64(read_len as u64));
+        Ok(())
+    }
+
+    // Writes the first `read_len` bytes spread across `buffers` back into the
+    // guest ranges named by `iovec`, filling entries in order and stopping once
+    // the read length is exhausted (a short read leaves trailing ranges
+    // untouched).
+    fn scatter<Mac: SupportMachine>(
+        machine: &mut Mac,
+        iovec: &[(u64, u64)],
+        buffers: &[Vec<u8>],
+        read_len: usize,
+    ) -> Result<(), Error> {
+        let mut remaining = read_len;
+        for ((addr, _), buffer) in iovec.iter().zip(buffers.iter()) {
+            if remaining == 0 {
+                break;
+            }
+            let chunk = remaining.min(buffer.len());
+            machine.memory_mut().store_bytes(*addr, &buffer[..chunk])?;
+            remaining -= chunk;
+        }
+        Ok(())
+    }
+
+    fn writev<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let guest_fd = machine.registers()[A0].to_u64();
+        let iov_addr = machine.registers()[A1].to_u64();
+        let iov_count = machine.registers()[A2].to_u64();
+        let fd = match self.host_fd(guest_fd) {
+            Ok(fd) => fd,
+            Err(e) => {
+                Self::set_errno(machine, e);
+                return Ok(());
+            }
+        };
+        if iov_count > IOV_MAX {
+            Self::set_errno(machine, Errno::EINVAL);
+            return Ok(());
+        }
+        let iovec = Self::load_iovec(machine, iov_addr, iov_count)?;
+        if Self::total_len(&iovec) > MAX_IO_BYTES as u64 {
+            Self::set_errno(machine, Errno::EINVAL);
+            return Ok(());
+        }
+        // Copy each guest range into its own host buffer, then write all of them
+        // to the fd with a single writev rather than one write per entry.
+        let mut buffers = Vec::with_capacity(iovec.len());
+        for (addr, len) in &iovec {
+            buffers.push(machine.memory_mut().load_bytes(*addr, *len)?);
+        }
+        let slices: Vec<IoVec<&[u8]>> = buffers.iter().map(|b| IoVec::from_slice(&b[..])).collect();
+        match writev(fd, &slices) {
+            Ok(write_len) => machine.set_register(A0, Mac::REG::from_u64(write_len as u64)),
+            Err(e) => Self::set_errno(machine, e),
+        }
+        Ok(())
+    }
+
+    fn openat<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let guest_dir_fd = machine.registers()[A0].to_i32();
+        let path_addr = machine.registers()[A1].to_u64();
+        let flags = machine.registers()[A2].to_u64();
+        let mode = machine.registers()[A3].to_u64();
+        // The dirfd names a guest descriptor just like every other fd argument,
+        // so it has to be translated through the allowlist before it reaches the
+        // host. AT_FDCWD is a sentinel rather than a real descriptor and is
+        // passed through unchanged.
+        let dir_fd = if guest_dir_fd == AT_FDCWD {
+            AT_FDCWD
+        } else {
+            match self.host_fd(guest_dir_fd as u64) {
+                Ok(fd) => fd,
+                Err(e) => {
+                    Self::set_errno(machine, e);
+                    return Ok(());
+                }
+            }
+        };
+        let path = self.load_cstr(machine, path_addr)?;
+        let oflag = OFlag::from_bits_truncate(flags as i32);
+        let fmode = Mode::from_bits_truncate(mode as u32 as nix::sys::stat::mode_t);
+        match openat(dir_fd, path.as_slice(), oflag, fmode) {
+            Ok(host_fd) => {
+                let guest_fd = self.next_fd;
+                self.next_fd += 1;
+                self.fds.insert(guest_fd, host_fd);
+                machine.set_register(A0, Mac::REG::from_u64(guest_fd));
+            }
+            Err(e) => Self::set_errno(machine, e),
+        }
+        Ok(())
+    }
+
+    fn close<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let guest_fd = machine.registers()[A0].to_u64();
+        let fd = match self.host_fd(guest_fd) {
+            Ok(fd) => fd,
+            Err(e) => {
+                Self::set_errno(machine, e);
+                return Ok(());
+            }
+        };
+        match close(fd) {
+            Ok(()) => {
+                self.fds.remove(&guest_fd);
+                machine.set_register(A0, Mac::REG::zero());
+            }
+            Err(e) => Self::set_errno(machine, e),
+        }
+        Ok(())
+    }
+
+    fn lseek<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let guest_fd = machine.registers()[A0].to_u64();
+        let offset = machine.registers()[A1].to_i64();
+        let whence = machine.registers()[A2].to_u64();
+        let fd = match self.host_fd(guest_fd) {
+            Ok(fd) => fd,
+            Err(e) => {
+                Self::set_errno(machine, e);
+                return Ok(());
+            }
+        };
+        let whence = match whence {
+            0 => Whence::SeekSet,
+            1 => Whence::SeekCur,
+            2 => Whence::SeekEnd,
+            _ => {
+                Self::set_errno(machine, Errno::EINVAL);
+                return Ok(());
+            }
+        };
+        match lseek(fd, offset, whence) {
+            Ok(pos) => machine.set_register(A0, Mac::REG::from_i64(pos)),
+            Err(e) => Self::set_errno(machine, e),
+        }
+        Ok(())
+    }
+
+    // Loads a NUL-terminated path from guest memory into a byte vector,
+    // excluding the terminator.
+    fn load_cstr<Mac: SupportMachine>(
+        &self,
+        machine: &mut Mac,
+        mut addr: u64,
+    ) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![];
+        loop {
+            let byte = machine
+                .memory_mut()
+                .load8(&Mac::REG::from_u64(addr))?
+                .to_u8();
+            if byte == 0 {
+                break;
+            }
+            bytes.push(byte);
+            addr += 1;
+        }
+        Ok(bytes)
+    }
+
     fn fstat<Mac: SupportMachine>(&mut self, machine: &mut Mac) -> Result<(), Error> {
-        let stat = match fstat(machine.registers()[A0].to_i32()) {
+        let fd = match self.host_fd(machine.registers()[A0].to_u64()) {
+            Ok(fd) => fd,
+            Err(e) => {
+                Self::set_errno(machine, e);
+                return Ok(());
+            }
+        };
+        let stat = match fstat(fd) {
             Ok(stat) => stat,
             Err(e) => {
                 println!("fstat error: {:?}", e);
-                machine.set_register(A0, Mac::REG::from_i8(-1));
+                Self::set_errno(machine, e);
                 return Ok(());
             }
         };
@@ -76,9 +395,57 @@ impl<Mac: SupportMachine> Syscalls<Mac> for Stdio {
 
     fn ecall(&mut self, machine: &mut Mac) -> Result<bool, Error> {
         match machine.registers()[A7].to_u64() {
+            56 => self.openat(machine)?,
+            57 => self.close(machine)?,
+            62 => self.lseek(machine)?,
+            63 => self.read(machine)?,
+            64 => self.write(machine)?,
+            65 => self.readv(machine)?,
+            66 => self.writev(machine)?,
             80 => self.fstat(machine)?,
             _ => return Ok(false),
         };
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_vm::{machine::VERSION0, CoreMachine, DefaultCoreMachine, SparseMemory, ISA_IMC};
+
+    fn machine() -> DefaultCoreMachine<u64, SparseMemory<u64>> {
+        DefaultCoreMachine::new(ISA_IMC, VERSION0, u64::max_value())
+    }
+
+    #[test]
+    fn load_iovec_parses_base_and_length_pairs() {
+        let mut m = machine();
+        let addr = 0x1000;
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&0x2000u64.to_le_bytes());
+        buf.extend_from_slice(&4u64.to_le_bytes());
+        buf.extend_from_slice(&0x3000u64.to_le_bytes());
+        buf.extend_from_slice(&8u64.to_le_bytes());
+        m.memory_mut().store_bytes(addr, &buf).unwrap();
+
+        let iovec = Stdio::load_iovec(&mut m, addr, 2).unwrap();
+        assert_eq!(iovec, vec![(0x2000, 4), (0x3000, 8)]);
+    }
+
+    #[test]
+    fn scatter_stops_at_short_read_boundary() {
+        let mut m = machine();
+        let iovec = vec![(0x2000, 4), (0x3000, 4)];
+        let buffers = vec![vec![1u8, 2, 3, 4], vec![5u8, 6, 7, 8]];
+
+        // Only 6 of the 8 available bytes were actually read.
+        Stdio::scatter(&mut m, &iovec, &buffers, 6).unwrap();
+
+        let first = m.memory_mut().load_bytes(0x2000, 4).unwrap();
+        let second = m.memory_mut().load_bytes(0x3000, 4).unwrap();
+        assert_eq!(&first[..], &[1, 2, 3, 4]);
+        // The second range only received 2 bytes; the tail stays zeroed.
+        assert_eq!(&second[..], &[5, 6, 0, 0]);
+    }
+}